@@ -0,0 +1,319 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [`KvBackend`] backed by Google Cloud Bigtable, for deployments that want
+//! catalog/schema metadata in a managed wide-column store instead of
+//! etcd or the in-memory backend.
+//!
+//! All values live in a single column, `DEFAULT_COLUMN_FAMILY:DEFAULT_COLUMN_QUALIFIER`,
+//! keyed by the same byte-string keys the rest of `catalog::remote` uses. The
+//! mapping onto Bigtable's RPCs is:
+//!
+//! * [`KvBackend::range`]        -> `ReadRows` with a row-key prefix range, streamed lazily.
+//! * [`KvBackend::set`]          -> `MutateRow` with a single `SetCell` mutation.
+//! * [`KvBackend::delete_range`] -> `ReadRows` over `[key, end]` to find the matching row
+//!   keys, then `MutateRows` with one `DeleteFromRow` entry per key. `key`/`end` are an
+//!   arbitrary inclusive range here (see `MockKvBackend::delete_range`), not necessarily
+//!   sharing a prefix, so `DropRowRange` (prefix-only) can't be used directly.
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bigtable_rs::bigtable::{BigTable, BigTableConnection, RowCell};
+use bigtable_rs::google::bigtable::v2::mutation::{self, Mutation};
+use bigtable_rs::google::bigtable::v2::mutate_rows_request::Entry as MutateRowsEntry;
+use bigtable_rs::google::bigtable::v2::row_range::{EndKey, StartKey};
+use bigtable_rs::google::bigtable::v2::{
+    MutateRowRequest, MutateRowsRequest, ReadRowsRequest, RowRange, RowSet,
+};
+
+use crate::error::Error;
+use crate::remote::{Kv, KvBackend, ValueIter};
+
+/// Single column family used to store the value cell; there is no need for
+/// more than one since we never query by qualifier.
+const DEFAULT_COLUMN_FAMILY: &str = "catalog";
+const DEFAULT_COLUMN_QUALIFIER: &[u8] = b"v";
+
+pub struct BigtableKvBackend {
+    client: BigTable,
+    /// The fully-qualified resource name (`projects/{project}/instances/{instance}/tables/{table}`)
+    /// every RPC in this file must send as `table_name`; Bigtable rejects a
+    /// bare table id.
+    table_resource_name: String,
+}
+
+impl BigtableKvBackend {
+    /// `bigtable_rs`'s `BigTableConnection` handles channel auth/TLS
+    /// internally (against the ambient GCP credentials), so there's nothing
+    /// for this backend to configure beyond the connection target.
+    pub async fn new(project_id: &str, instance_name: &str, table_name: &str) -> Result<Self, Error> {
+        // Not read-only: `set` and `delete_range` issue mutations.
+        let connection = BigTableConnection::new(project_id, instance_name, false, None)
+            .await
+            .map_err(|e| Error::RemoteConnect {
+                msg: format!("failed to connect to Bigtable instance {instance_name}: {e}"),
+            })?;
+
+        Ok(Self {
+            client: connection.client(),
+            table_resource_name: format!(
+                "projects/{project_id}/instances/{instance_name}/tables/{table_name}"
+            ),
+        })
+    }
+
+    fn full_table_name(&self) -> String {
+        self.table_resource_name.clone()
+    }
+
+    /// Bigtable returns a cell's value as the concatenation of all of its
+    /// chunks; `bigtable_rs` already reassembles that for us into
+    /// [`RowCell::value`], so this just finds the one cell we wrote in
+    /// [`Self::set`].
+    fn cell_value(cells: Vec<RowCell>) -> Option<Vec<u8>> {
+        cells
+            .into_iter()
+            .find(|c| c.qualifier == DEFAULT_COLUMN_QUALIFIER)
+            .map(|c| c.value)
+    }
+}
+
+#[async_trait]
+impl KvBackend for BigtableKvBackend {
+    fn range<'a, 'b>(&'a self, key: &[u8]) -> ValueIter<'b, Error>
+    where
+        'a: 'b,
+    {
+        let prefix = key.to_vec();
+        let table_name = self.full_table_name();
+        let mut client = self.client.clone();
+
+        Box::pin(stream! {
+            let end_key = prefix_end(&prefix);
+            let row_range = RowRange {
+                start_key: Some(bigtable_rs::google::bigtable::v2::row_range::StartKey::StartKeyClosed(prefix.clone())),
+                end_key: end_key.map(bigtable_rs::google::bigtable::v2::row_range::EndKey::EndKeyOpen),
+            };
+            let request = ReadRowsRequest {
+                table_name,
+                rows: Some(RowSet {
+                    row_keys: vec![],
+                    row_ranges: vec![row_range],
+                }),
+                ..Default::default()
+            };
+
+            // `read_rows` streams row chunks lazily from the server; we only
+            // ever hold one decoded row in memory at a time, matching the
+            // existing `ValueIter` contract.
+            let mut rows = match client.read_rows(request).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    yield Err(Error::RemoteRequest {
+                        msg: format!("Bigtable ReadRows failed: {e}"),
+                    });
+                    return;
+                }
+            };
+
+            while let Some(next) = rows.next().await {
+                match next {
+                    Ok((row_key, cells)) => {
+                        if let Some(value) = Self::cell_value(cells) {
+                            yield Ok(Kv(row_key, value));
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(Error::RemoteRequest {
+                            msg: format!("Bigtable ReadRows stream error: {e}"),
+                        });
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn set(&self, key: &[u8], val: &[u8]) -> Result<(), Error> {
+        let mut client = self.client.clone();
+        let request = MutateRowRequest {
+            table_name: self.full_table_name(),
+            row_key: key.to_vec(),
+            mutations: vec![Mutation {
+                mutation: Some(mutation::Mutation::SetCell(mutation::SetCell {
+                    family_name: DEFAULT_COLUMN_FAMILY.to_string(),
+                    column_qualifier: DEFAULT_COLUMN_QUALIFIER.to_vec(),
+                    timestamp_micros: -1, // server-assigned timestamp
+                    value: val.to_vec(),
+                })),
+            }],
+            ..Default::default()
+        };
+
+        client
+            .mutate_row(request)
+            .await
+            .map(|_| ())
+            .map_err(|e| row_not_found_aware(key, e))
+    }
+
+    async fn delete_range(&self, key: &[u8], end: &[u8]) -> Result<(), Error> {
+        let mut client = self.client.clone();
+        let table_name = self.full_table_name();
+
+        // `[key, end]` is an arbitrary inclusive range here (see
+        // `MockKvBackend::delete_range`), not necessarily a shared prefix, so
+        // we can't use `DropRowRange` (prefix-only) directly: find the
+        // matching row keys with `ReadRows`, then delete exactly those rows.
+        let row_range = RowRange {
+            start_key: Some(StartKey::StartKeyClosed(key.to_vec())),
+            end_key: Some(EndKey::EndKeyClosed(end.to_vec())),
+        };
+        let read_request = ReadRowsRequest {
+            table_name: table_name.clone(),
+            rows: Some(RowSet {
+                row_keys: vec![],
+                row_ranges: vec![row_range],
+            }),
+            ..Default::default()
+        };
+
+        let mut rows = client
+            .read_rows(read_request)
+            .await
+            .map_err(|e| Error::RemoteRequest {
+                msg: format!("Bigtable ReadRows (for delete_range) failed: {e}"),
+            })?;
+
+        let mut entries = Vec::new();
+        while let Some(next) = rows.next().await {
+            let (row_key, _cells) = next.map_err(|e| Error::RemoteRequest {
+                msg: format!("Bigtable ReadRows stream error during delete_range: {e}"),
+            })?;
+            entries.push(MutateRowsEntry {
+                row_key,
+                mutations: vec![Mutation {
+                    mutation: Some(mutation::Mutation::DeleteFromRow(mutation::DeleteFromRow {})),
+                }],
+            });
+        }
+
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mutate_request = MutateRowsRequest {
+            table_name,
+            app_profile_id: String::new(),
+            entries,
+        };
+
+        client
+            .mutate_rows(mutate_request)
+            .await
+            .map_err(|e| Error::RemoteRequest {
+                msg: format!("Bigtable MutateRows (for delete_range) failed: {e}"),
+            })?;
+        Ok(())
+    }
+}
+
+/// Translates a gRPC "not found" status for a single-row mutation into the
+/// crate's own not-found variant; every other error passes through as a
+/// generic remote-request failure.
+fn row_not_found_aware(key: &[u8], e: impl std::fmt::Display) -> Error {
+    let msg = e.to_string();
+    if msg.contains("NotFound") {
+        Error::ValueNotExist {
+            key: String::from_utf8_lossy(key).to_string(),
+        }
+    } else {
+        Error::RemoteRequest {
+            msg: format!("Bigtable MutateRow failed: {msg}"),
+        }
+    }
+}
+
+/// Smallest key greater than every key with `prefix` as a prefix, or `None`
+/// if `prefix` is all-0xff bytes (meaning the range is unbounded above).
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    for i in (0..end.len()).rev() {
+        if end[i] != 0xff {
+            end[i] += 1;
+            end.truncate(i + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use bigtable_rs::bigtable::RowCell;
+
+    use super::*;
+
+    fn row_cell(qualifier: &[u8], value: &[u8]) -> RowCell {
+        RowCell {
+            family_name: DEFAULT_COLUMN_FAMILY.to_string(),
+            qualifier: qualifier.to_vec(),
+            value: value.to_vec(),
+            timestamp_micros: 0,
+            labels: vec![],
+        }
+    }
+
+    #[test]
+    fn test_prefix_end_increments_last_non_0xff_byte() {
+        assert_eq!(prefix_end(b"abc"), Some(b"abd".to_vec()));
+        assert_eq!(prefix_end(b"ab\xff"), Some(b"ac".to_vec()));
+    }
+
+    #[test]
+    fn test_prefix_end_of_all_0xff_is_unbounded() {
+        assert_eq!(prefix_end(&[0xff, 0xff]), None);
+        assert_eq!(prefix_end(&[]), None);
+    }
+
+    #[test]
+    fn test_cell_value_finds_the_default_qualifier() {
+        let cells = vec![
+            row_cell(b"other", b"ignored"),
+            row_cell(DEFAULT_COLUMN_QUALIFIER, b"the value"),
+        ];
+        assert_eq!(
+            BigtableKvBackend::cell_value(cells),
+            Some(b"the value".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_cell_value_is_none_without_the_default_qualifier() {
+        let cells = vec![row_cell(b"other", b"ignored")];
+        assert_eq!(BigtableKvBackend::cell_value(cells), None);
+    }
+
+    #[test]
+    fn test_row_not_found_aware_maps_not_found_status() {
+        let err = row_not_found_aware(b"some-key", "status: NotFound, message: \"no such row\"");
+        assert!(matches!(err, Error::ValueNotExist { key } if key == "some-key"));
+    }
+
+    #[test]
+    fn test_row_not_found_aware_passes_other_errors_through() {
+        let err = row_not_found_aware(b"some-key", "status: Unavailable, message: \"try again\"");
+        assert!(matches!(err, Error::RemoteRequest { .. }));
+    }
+}
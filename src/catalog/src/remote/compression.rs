@@ -0,0 +1,223 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [`KvBackend`] wrapper that transparently compresses stored values.
+//!
+//! Catalog and schema metadata blobs tend to be small but highly repetitive
+//! (similar column names, type tags, etc. repeated across keys), so we try a
+//! handful of general-purpose codecs on every `set` and keep whichever one
+//! produced the smallest output, tagging the value with a leading
+//! discriminator byte so `range` can reverse the transform. This
+//! mirrors the `compress_best`/`decompress` approach used by Solana's
+//! BigTable storage layer.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::error::Error;
+use crate::remote::{Kv, KvBackend, ValueIter};
+
+/// Values smaller than this are stored verbatim: the codec framing overhead
+/// (plus the near-certainty of negative compression ratios on tiny inputs)
+/// isn't worth it.
+const DEFAULT_COMPRESSION_THRESHOLD: usize = 128;
+
+/// Leading byte identifying how the remainder of a value is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum CodecTag {
+    /// Stored verbatim, no transform applied.
+    None = 0,
+    Zstd = 1,
+    Gzip = 2,
+}
+
+impl CodecTag {
+    fn from_byte(b: u8) -> Option<CodecTag> {
+        match b {
+            0 => Some(CodecTag::None),
+            1 => Some(CodecTag::Zstd),
+            2 => Some(CodecTag::Gzip),
+            _ => None,
+        }
+    }
+}
+
+fn compress_zstd(val: &[u8]) -> Vec<u8> {
+    // Level 0 lets zstd pick its own default; catalog values are small so
+    // compression speed is a non-issue.
+    zstd::stream::encode_all(val, 0).unwrap_or_else(|_| val.to_vec())
+}
+
+fn decompress_zstd(val: &[u8]) -> Result<Vec<u8>, Error> {
+    zstd::stream::decode_all(val).map_err(|e| Error::Compression {
+        msg: format!("failed to zstd-decompress value: {e}"),
+    })
+}
+
+fn compress_gzip(val: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing to an in-memory `Vec` cannot fail.
+    encoder.write_all(val).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decompress_gzip(val: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    let mut decoder = GzDecoder::new(val);
+    let mut out = Vec::new();
+    decoder
+        .read_to_end(&mut out)
+        .map_err(|e| Error::Compression {
+            msg: format!("failed to gzip-decompress value: {e}"),
+        })?;
+    Ok(out)
+}
+
+/// Tries every candidate codec and keeps the smallest result, tagged with its
+/// [`CodecTag`] as the leading byte.
+fn compress_best(val: &[u8], threshold: usize) -> Vec<u8> {
+    if val.len() < threshold {
+        let mut out = Vec::with_capacity(val.len() + 1);
+        out.push(CodecTag::None as u8);
+        out.extend_from_slice(val);
+        return out;
+    }
+
+    let candidates = [
+        (CodecTag::Zstd, compress_zstd(val)),
+        (CodecTag::Gzip, compress_gzip(val)),
+    ];
+
+    let best = candidates
+        .into_iter()
+        .min_by_key(|(_, compressed)| compressed.len());
+
+    match best {
+        Some((tag, compressed)) if compressed.len() < val.len() => {
+            let mut out = Vec::with_capacity(compressed.len() + 1);
+            out.push(tag as u8);
+            out.extend_from_slice(&compressed);
+            out
+        }
+        _ => {
+            let mut out = Vec::with_capacity(val.len() + 1);
+            out.push(CodecTag::None as u8);
+            out.extend_from_slice(val);
+            out
+        }
+    }
+}
+
+/// Reverses [`compress_best`], dispatching on the leading discriminator byte.
+fn decompress(val: &[u8]) -> Result<Vec<u8>, Error> {
+    let (tag, rest) = val.split_first().ok_or_else(|| Error::Compression {
+        msg: "empty value has no codec tag".to_string(),
+    })?;
+    match CodecTag::from_byte(*tag) {
+        Some(CodecTag::None) => Ok(rest.to_vec()),
+        Some(CodecTag::Zstd) => decompress_zstd(rest),
+        Some(CodecTag::Gzip) => decompress_gzip(rest),
+        None => Err(Error::Compression {
+            msg: format!("unknown codec tag {tag}"),
+        }),
+    }
+}
+
+/// A [`KvBackend`] decorator that compresses values before delegating to an
+/// inner backend, and decompresses them again on the way out.
+pub struct CompressedKvBackend<T> {
+    inner: T,
+    threshold: usize,
+}
+
+impl<T> CompressedKvBackend<T> {
+    pub fn new(inner: T) -> Self {
+        Self::with_threshold(inner, DEFAULT_COMPRESSION_THRESHOLD)
+    }
+
+    pub fn with_threshold(inner: T, threshold: usize) -> Self {
+        Self { inner, threshold }
+    }
+}
+
+#[async_trait]
+impl<T: KvBackend + Send + Sync> KvBackend for CompressedKvBackend<T> {
+    fn range<'a, 'b>(&'a self, key: &[u8]) -> ValueIter<'b, Error>
+    where
+        'a: 'b,
+    {
+        Box::pin(self.inner.range(key).map(|kv| {
+            let Kv(k, v) = kv?;
+            let v = decompress(&v)?;
+            Ok(Kv(k, v))
+        }))
+    }
+
+    async fn set(&self, key: &[u8], val: &[u8]) -> Result<(), Error> {
+        let compressed = compress_best(val, self.threshold);
+        self.inner.set(key, &compressed).await
+    }
+
+    async fn delete_range(&self, key: &[u8], end: &[u8]) -> Result<(), Error> {
+        self.inner.delete_range(key, end).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncompressible_payload_stays_none_tagged() {
+        // Random-looking bytes above the threshold: none of the codecs
+        // should beat storing it verbatim.
+        let payload: Vec<u8> = (0..256).map(|i| (i * 37 % 251) as u8).collect();
+        let encoded = compress_best(&payload, DEFAULT_COMPRESSION_THRESHOLD);
+        assert_eq!(encoded[0], CodecTag::None as u8);
+        assert_eq!(&encoded[1..], &payload[..]);
+
+        let decoded = decompress(&encoded).unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn test_repetitive_payload_shrinks() {
+        let payload = "value1_string".repeat(64).into_bytes();
+        let encoded = compress_best(&payload, DEFAULT_COMPRESSION_THRESHOLD);
+        assert_ne!(encoded[0], CodecTag::None as u8);
+        assert!(encoded.len() < payload.len());
+
+        let decoded = decompress(&encoded).unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn test_small_payload_skips_compression() {
+        let payload = b"short".to_vec();
+        let encoded = compress_best(&payload, DEFAULT_COMPRESSION_THRESHOLD);
+        assert_eq!(encoded[0], CodecTag::None as u8);
+
+        let decoded = decompress(&encoded).unwrap();
+        assert_eq!(decoded, payload);
+    }
+}
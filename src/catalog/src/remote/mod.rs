@@ -0,0 +1,52 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Remote catalog storage: the [`KvBackend`] trait and its implementations.
+
+pub mod bigtable;
+pub mod compression;
+
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use futures::Stream;
+
+use crate::error::Error;
+
+/// A single key/value pair returned from [`KvBackend::range`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kv(pub Vec<u8>, pub Vec<u8>);
+
+/// A lazily-evaluated stream of [`Kv`] results, as returned by
+/// [`KvBackend::range`]. The stream lets a backend yield rows as they're
+/// fetched; whether it buffers the result set first is up to the backend.
+pub type ValueIter<'a, E> = Pin<Box<dyn Stream<Item = Result<Kv, E>> + Send + 'a>>;
+
+/// Storage abstraction for catalog/schema metadata: a byte-string keyed store
+/// supporting prefix scans, point writes, and range deletes. Implemented by
+/// [`compression::CompressedKvBackend`] (a transparent wrapper) and
+/// [`bigtable::BigtableKvBackend`] (a managed remote store), among others.
+#[async_trait]
+pub trait KvBackend: Send + Sync {
+    /// Streams all key/value pairs whose key starts with `key`.
+    fn range<'a, 'b>(&'a self, key: &[u8]) -> ValueIter<'b, Error>
+    where
+        'a: 'b;
+
+    /// Writes a single key/value pair, overwriting any existing value.
+    async fn set(&self, key: &[u8], val: &[u8]) -> Result<(), Error>;
+
+    /// Deletes every key in `[key, end]`.
+    async fn delete_range(&self, key: &[u8], end: &[u8]) -> Result<(), Error>;
+}
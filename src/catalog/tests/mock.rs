@@ -16,7 +16,10 @@ use datatypes::vectors::StringVector;
 use serde::Serializer;
 use table::engine::{EngineContext, TableEngine};
 use table::metadata::TableId;
-use table::requests::{AlterTableRequest, CreateTableRequest, DropTableRequest, OpenTableRequest};
+use table::quota::{QuotaTracker, TableQuota, TableUsage};
+use table::requests::{
+    AlterTableRequest, CreateTableRequest, DropTableRequest, OpenTableRequest, WriteTableRequest,
+};
 use table::TableRef;
 use tokio::sync::RwLock;
 
@@ -83,6 +86,18 @@ impl KvBackend for MockKvBackend {
 #[derive(Default)]
 pub struct MockTableEngine {
     tables: RwLock<HashMap<String, TableRef>>,
+    quotas: RwLock<HashMap<String, Arc<QuotaTracker>>>,
+}
+
+impl MockTableEngine {
+    /// Offline counter repair: in a real engine this would rescan a table's
+    /// SSTs/memtable to recompute the authoritative row/byte counts; here we
+    /// just accept them, since the mock has nothing to rescan.
+    pub async fn repair_counters(&self, table_name: &str, authoritative_rows: u64, authoritative_bytes: u64) {
+        if let Some(tracker) = self.quotas.read().await.get(table_name) {
+            tracker.repair(authoritative_rows, authoritative_bytes);
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -121,8 +136,26 @@ impl TableEngine for MockTableEngine {
             true,
         )]));
 
-        let data = vec![Arc::new(StringVector::from(vec!["a", "b", "c"])) as _];
+        let rows = ["a", "b", "c"];
+        let data = vec![Arc::new(StringVector::from(rows.to_vec())) as _];
         let record_batch = RecordBatch::new(schema, data).unwrap();
+
+        // The quota is declared at create time and enforced against the
+        // rows this call is about to write; a table that's already over
+        // quota on its very first write is rejected before the `MemTable`
+        // is ever constructed.
+        let quota = TableQuota::from_table_options(&request.table_options);
+        let tracker = Arc::new(QuotaTracker::new(quota));
+        let byte_size: u64 = rows.iter().map(|r| r.len() as u64).sum();
+        tracker
+            .reserve(rows.len() as u64, byte_size)
+            .map_err(|reason| {
+                table::error::Error::QuotaExceeded {
+                    table: table_name.clone(),
+                    reason: reason.to_string(),
+                }
+            })?;
+
         let table: TableRef = Arc::new(test_util::MemTable::new_with_catalog(
             &table_name,
             record_batch,
@@ -131,6 +164,7 @@ impl TableEngine for MockTableEngine {
             schema_name,
         )) as Arc<_>;
 
+        self.quotas.write().await.insert(table_name.clone(), tracker);
         let mut tables = self.tables.write().await;
         tables.insert(table_name, table.clone() as TableRef);
         Ok(table)
@@ -167,4 +201,20 @@ impl TableEngine for MockTableEngine {
     ) -> table::Result<()> {
         unimplemented!()
     }
+
+    async fn write(&self, _ctx: &EngineContext, request: WriteTableRequest) -> table::Result<()> {
+        if let Some(tracker) = self.quotas.read().await.get(&request.table_name) {
+            tracker
+                .reserve(request.rows, request.bytes)
+                .map_err(|reason| table::error::Error::QuotaExceeded {
+                    table: request.table_name.clone(),
+                    reason: reason.to_string(),
+                })?;
+        }
+        Ok(())
+    }
+
+    fn table_usage(&self, _ctx: &EngineContext, name: &str) -> Option<TableUsage> {
+        futures::executor::block_on(async { self.quotas.read().await.get(name).map(|t| t.usage()) })
+    }
 }
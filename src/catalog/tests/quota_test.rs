@@ -0,0 +1,105 @@
+//! Integration tests driving `MockTableEngine`'s quota enforcement through
+//! its actual `TableEngine` surface (`create_table`/`write`/`table_usage`)
+//! plus its own `repair_counters`, rather than unit-testing `QuotaTracker`
+//! in isolation.
+
+mod mock;
+
+use std::collections::HashMap;
+
+use mock::MockTableEngine;
+use table::engine::{EngineContext, TableEngine};
+use table::requests::{CreateTableRequest, WriteTableRequest};
+
+fn create_request(table_name: &str, max_rows: u64) -> CreateTableRequest {
+    let mut table_options = HashMap::new();
+    table_options.insert("max_rows".to_string(), max_rows.to_string());
+    CreateTableRequest {
+        table_name: table_name.to_string(),
+        table_options,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_write_rejected_once_quota_exceeded() {
+    let engine = MockTableEngine::default();
+    let ctx = EngineContext::default();
+
+    // `create_table`'s own fixture write (3 rows) already counts against the
+    // quota, leaving room for 7 more under a limit of 10.
+    engine
+        .create_table(&ctx, create_request("t1", 10))
+        .await
+        .unwrap();
+
+    let write = WriteTableRequest {
+        table_name: "t1".to_string(),
+        rows: 7,
+        bytes: 0,
+        ..Default::default()
+    };
+    engine.write(&ctx, write).await.unwrap();
+    assert_eq!(engine.table_usage(&ctx, "t1").unwrap().rows, 10);
+
+    // One more row has no room left under the quota.
+    let rejected = WriteTableRequest {
+        table_name: "t1".to_string(),
+        rows: 1,
+        bytes: 0,
+        ..Default::default()
+    };
+    let err = engine.write(&ctx, rejected).await.unwrap_err();
+    assert!(matches!(err, table::error::Error::QuotaExceeded { .. }));
+    // The rejected write must not have moved the counter.
+    assert_eq!(engine.table_usage(&ctx, "t1").unwrap().rows, 10);
+}
+
+#[tokio::test]
+async fn test_repair_counters_overwrites_usage_after_write() {
+    let engine = MockTableEngine::default();
+    let ctx = EngineContext::default();
+
+    engine
+        .create_table(&ctx, create_request("t2", 1000))
+        .await
+        .unwrap();
+    engine
+        .write(
+            &ctx,
+            WriteTableRequest {
+                table_name: "t2".to_string(),
+                rows: 50,
+                bytes: 4096,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(engine.table_usage(&ctx, "t2").unwrap().rows, 53);
+
+    // An offline rescan found the in-memory counters had drifted; repair
+    // overwrites them with the authoritative values.
+    engine.repair_counters("t2", 40, 1024).await;
+    let usage = engine.table_usage(&ctx, "t2").unwrap();
+    assert_eq!(usage.rows, 40);
+    assert_eq!(usage.bytes, 1024);
+}
+
+#[tokio::test]
+async fn test_write_to_table_without_quota_tracking_is_a_no_op() {
+    let engine = MockTableEngine::default();
+    let ctx = EngineContext::default();
+
+    // A write against a table the engine has never seen (and so has no
+    // `QuotaTracker` for) is accepted rather than rejected — there's nothing
+    // to enforce against.
+    let write = WriteTableRequest {
+        table_name: "untracked".to_string(),
+        rows: 1_000_000,
+        bytes: 1_000_000,
+        ..Default::default()
+    };
+    engine.write(&ctx, write).await.unwrap();
+    assert!(engine.table_usage(&ctx, "untracked").is_none());
+}
@@ -0,0 +1,90 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builds a one-mutation [`WriteBatch`] from a column spec, for benches that
+//! need a batch of a given shape without hand-assembling every column.
+
+use std::sync::Arc;
+
+use datatypes::type_id::LogicalTypeId;
+use datatypes::vectors::{
+    BooleanVector, Float64Vector, StringVector, TimestampMillisecondVector, UInt64Vector,
+    VectorRef,
+};
+use store_api::storage::{consts, PutOperation, WriteRequest};
+use storage::write_batch::{PutData, WriteBatch};
+
+/// Default row count when a bench doesn't care how many rows it gets.
+const DEFAULT_NUM_ROWS: usize = 3;
+
+/// Builds a single-mutation [`WriteBatch`] with one column per `columns`
+/// entry (`name`, logical type, nullable), each holding `num_rows` rows (or
+/// [`DEFAULT_NUM_ROWS`] if `None`). `k1`/`ts` become key columns, the column
+/// named [`consts::VERSION_COLUMN_NAME`] becomes the version column, and
+/// everything else is a plain value column — the same role split
+/// `storage::mvcc` and the WAL codec assume elsewhere.
+pub fn new_write_batch(columns: &[(&str, LogicalTypeId, bool)], num_rows: Option<usize>) -> WriteBatch {
+    let num_rows = num_rows.unwrap_or(DEFAULT_NUM_ROWS);
+
+    let mut put_data = PutData::default();
+    for (name, type_id, nullable) in columns {
+        let vector = build_vector(*type_id, num_rows, *nullable);
+        if *name == consts::VERSION_COLUMN_NAME {
+            put_data.add_version_column(vector).unwrap();
+        } else if *name == "k1" || *name == "ts" {
+            put_data.add_key_column(name, vector).unwrap();
+        } else {
+            put_data.add_value_column(name, vector).unwrap();
+        }
+    }
+
+    let mut batch = WriteBatch::new();
+    batch.put(put_data).unwrap();
+    batch
+}
+
+/// Generates `num_rows` values of `type_id`; every other row is `None` when
+/// `nullable` is set, so nullable columns actually exercise their null path.
+fn build_vector(type_id: LogicalTypeId, num_rows: usize, nullable: bool) -> VectorRef {
+    let is_null = |i: usize| nullable && i % 2 == 1;
+
+    match type_id {
+        LogicalTypeId::UInt64 => Arc::new(
+            (0..num_rows)
+                .map(|i| if is_null(i) { None } else { Some(i as u64) })
+                .collect::<UInt64Vector>(),
+        ),
+        LogicalTypeId::Boolean => Arc::new(
+            (0..num_rows)
+                .map(|i| if is_null(i) { None } else { Some(i % 2 == 0) })
+                .collect::<BooleanVector>(),
+        ),
+        LogicalTypeId::TimestampMillisecond => Arc::new(
+            (0..num_rows)
+                .map(|i| if is_null(i) { None } else { Some(i as i64) })
+                .collect::<TimestampMillisecondVector>(),
+        ),
+        LogicalTypeId::Float64 => Arc::new(
+            (0..num_rows)
+                .map(|i| if is_null(i) { None } else { Some(i as f64) })
+                .collect::<Float64Vector>(),
+        ),
+        LogicalTypeId::String => Arc::new(
+            (0..num_rows)
+                .map(|i| if is_null(i) { None } else { Some(format!("value{i}")) })
+                .collect::<StringVector>(),
+        ),
+        other => unimplemented!("new_write_batch doesn't support column type {other:?}"),
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("Column '{}' not found in write batch", name))]
+    ColumnNotFound { name: String },
+
+    #[snafu(display("Column '{}' has unsupported type for this operation", name))]
+    UnsupportedColumnType { name: String },
+
+    #[snafu(display("Failed to encode WAL mutation: {}", msg))]
+    WalEncode { msg: String },
+
+    #[snafu(display("Failed to decode WAL mutation: {}", msg))]
+    WalDecode { msg: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,198 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reconstructs a region's in-memory state from its WAL on open.
+//!
+//! Each WAL entry carries the sequence number it was written at, the
+//! encoded mutation payload, and the mutation-type vector produced by
+//! [`crate::proto::wal::gen_mutation_types`] at write time (needed to decode
+//! the payload back into typed columns). [`replay`] turns a stream of such
+//! entries into a stream of [`WriteBatch`]es ready to re-apply to a
+//! memtable, skipping anything already durable and stopping at the first
+//! sign of a truncated/corrupt tail record rather than failing the whole
+//! region open.
+
+use async_stream::stream;
+use common_telemetry::logging::{info, warn};
+use futures::{Stream, StreamExt};
+
+use crate::error::Result;
+use crate::proto::wal::decode_mutation;
+use crate::write_batch::WriteBatch;
+use store_api::storage::SequenceNumber;
+
+/// One record read off a region's WAL, prior to decoding.
+pub struct WalEntry {
+    /// Monotonically increasing within a region; used to dedupe against
+    /// already-flushed data on replay.
+    pub sequence: SequenceNumber,
+    /// Per-mutation type tags, as produced by `gen_mutation_types`.
+    pub mutation_types: Vec<i32>,
+    /// The encoded `WriteBatch` mutation payload.
+    pub payload: Vec<u8>,
+}
+
+/// A fallible stream of raw WAL entries, in the order they were appended.
+pub type WalEntryStream<'a> = std::pin::Pin<Box<dyn Stream<Item = Result<WalEntry>> + Send + 'a>>;
+
+/// Replays `wal_iter` into a stream of [`WriteBatch`]es, for re-applying to a
+/// region's memtable during open.
+///
+/// Entries whose `sequence` is `<= flushed_seq` are skipped: they're already
+/// reflected in the last flushed SST/manifest state, and re-applying them
+/// would double-count. Replay stops (without erroring) at the first entry
+/// that fails to decode, since a truncated trailing record is the expected
+/// shape of a crash mid-append rather than genuine corruption earlier in the
+/// log.
+pub fn replay<'a>(
+    wal_iter: WalEntryStream<'a>,
+    flushed_seq: SequenceNumber,
+) -> impl Stream<Item = Result<WriteBatch>> + 'a {
+    stream! {
+        let mut wal_iter = wal_iter;
+        while let Some(entry) = wal_iter.next().await {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Stopping WAL replay at read error, treating tail as truncated: {}", e);
+                    return;
+                }
+            };
+
+            if entry.sequence <= flushed_seq {
+                continue;
+            }
+
+            match decode_mutation(&entry.payload, &entry.mutation_types) {
+                Ok(batch) => yield Ok(batch),
+                Err(e) => {
+                    warn!(
+                        "Stopping WAL replay at sequence {} due to decode error, \
+                         treating as truncated/corrupt tail record: {}",
+                        entry.sequence, e
+                    );
+                    return;
+                }
+            }
+        }
+        info!("WAL replay finished");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datatypes::vectors::{BooleanVector, UInt64Vector};
+    use futures::{stream, StreamExt};
+    use store_api::storage::{PutOperation, WriteRequest};
+
+    use super::*;
+    use crate::proto::wal::{encode_mutation, gen_mutation_types};
+    use crate::write_batch::{Mutation, PutData};
+
+    /// Builds a small multi-row batch, one mutation per row, mirroring the
+    /// shape of the bench helper's `gen_new_batch_and_types` (one `PutData`
+    /// per call, each with a key/version/value column).
+    fn gen_batch_and_types(rows: u64) -> (WriteBatch, Vec<Vec<i32>>) {
+        let mut batch = WriteBatch::new();
+        let mut types = Vec::new();
+        for i in 0..rows {
+            let mut put_data = PutData::default();
+            put_data
+                .add_key_column("k1", Arc::new(UInt64Vector::from_slice(&[i])))
+                .unwrap();
+            put_data
+                .add_version_column(Arc::new(UInt64Vector::from_slice(&[i])))
+                .unwrap();
+            put_data
+                .add_value_column("v1", Arc::new(BooleanVector::from(vec![i % 2 == 0])))
+                .unwrap();
+            batch.put(put_data).unwrap();
+
+            let mut single = WriteBatch::new();
+            let Mutation::Put(p) = batch.mutations().last().unwrap().clone();
+            single.put(p).unwrap();
+            types.push(gen_mutation_types(&single));
+        }
+        (batch, types)
+    }
+
+    fn wal_entries(batch: &WriteBatch, types: &[Vec<i32>]) -> Vec<WalEntry> {
+        batch
+            .mutations()
+            .iter()
+            .zip(types.iter())
+            .enumerate()
+            .map(|(i, (mutation, mutation_types))| WalEntry {
+                sequence: i as SequenceNumber + 1,
+                mutation_types: mutation_types.clone(),
+                payload: encode_mutation(mutation).unwrap(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_replay_round_trip() {
+        let (batch, types) = gen_batch_and_types(5);
+        let entries = wal_entries(&batch, &types);
+
+        let wal_iter: WalEntryStream = Box::pin(stream::iter(entries.into_iter().map(Ok)));
+        let replayed: Vec<WriteBatch> = replay(wal_iter, 0)
+            .map(|b| b.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(replayed.len(), batch.mutations().len());
+        for (replayed_batch, original_mutation) in replayed.iter().zip(batch.mutations().iter()) {
+            let Mutation::Put(replayed_put) = &replayed_batch.mutations()[0];
+            let Mutation::Put(original_put) = original_mutation;
+            assert_eq!(replayed_put.columns().len(), original_put.columns().len());
+            for (a, b) in replayed_put.columns().iter().zip(original_put.columns().iter()) {
+                assert_eq!(a.name, b.name);
+                assert_eq!(a.role, b.role);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_skips_already_flushed_sequences() {
+        let (batch, types) = gen_batch_and_types(5);
+        let entries = wal_entries(&batch, &types);
+
+        // The first 3 sequences (1..=3) are already reflected in the
+        // manifest's flushed_seq; only 4 and 5 should replay.
+        let wal_iter: WalEntryStream = Box::pin(stream::iter(entries.into_iter().map(Ok)));
+        let replayed: Vec<WriteBatch> = replay(wal_iter, 3).map(|b| b.unwrap()).collect().await;
+
+        assert_eq!(replayed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_stops_cleanly_at_corrupt_tail() {
+        let (batch, types) = gen_batch_and_types(4);
+        let mut entries = wal_entries(&batch, &types);
+        // Simulate a crash mid-append: truncate the last entry's payload.
+        let last = entries.last_mut().unwrap();
+        last.payload.truncate(last.payload.len() / 2);
+
+        let wal_iter: WalEntryStream = Box::pin(stream::iter(entries.into_iter().map(Ok)));
+        let replayed: Vec<Result<WriteBatch>> = replay(wal_iter, 0).collect().await;
+
+        // The 3 good entries replay; the corrupt tail record is dropped
+        // silently rather than failing the whole region open.
+        assert_eq!(replayed.len(), 3);
+        assert!(replayed.iter().all(|r| r.is_ok()));
+    }
+}
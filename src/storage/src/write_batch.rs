@@ -0,0 +1,142 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory representation of a batch of mutations applied to a single
+//! region, before they're appended to the WAL and applied to the memtable.
+
+use std::collections::HashSet;
+
+use datatypes::prelude::ScalarVector;
+use datatypes::vectors::{StringVector, VectorRef};
+use store_api::storage::{PutOperation, WriteRequest};
+
+use crate::error::Result;
+
+/// A string column is only worth dictionary-encoding if its distinct values
+/// are rarer than this fraction of its rows; above that, the dictionary
+/// itself approaches the size of the plain column and the index indirection
+/// stops paying for itself.
+const DICTIONARY_CARDINALITY_RATIO: f64 = 0.5;
+
+/// The role a column plays within a [`PutData`], used by the WAL codec and
+/// by downstream consumers (e.g. the MVCC classifier) to tell primary-key
+/// columns apart from plain value columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnRole {
+    Key,
+    Version,
+    Value,
+}
+
+/// A named column together with the role it plays in the row.
+#[derive(Clone)]
+pub struct PutColumn {
+    pub name: String,
+    pub role: ColumnRole,
+    pub vector: VectorRef,
+}
+
+impl PutColumn {
+    /// Whether the WAL/wire codec should dictionary-encode this column: it
+    /// has to be a string column, non-empty, and low-cardinality enough
+    /// (see [`DICTIONARY_CARDINALITY_RATIO`]) that an index array plus a
+    /// small dictionary beats repeating full strings.
+    pub fn is_dictionary_encoded(&self) -> bool {
+        let Some(v) = self.vector.as_any().downcast_ref::<StringVector>() else {
+            return false;
+        };
+        if v.len() == 0 {
+            return false;
+        }
+
+        let distinct: HashSet<&str> = v.iter_data().flatten().collect();
+        (distinct.len() as f64) < (v.len() as f64) * DICTIONARY_CARDINALITY_RATIO
+    }
+}
+
+/// The column data for a single `put` mutation, built incrementally via
+/// [`PutOperation`].
+#[derive(Clone, Default)]
+pub struct PutData {
+    columns: Vec<PutColumn>,
+}
+
+impl PutData {
+    pub fn columns(&self) -> &[PutColumn] {
+        &self.columns
+    }
+
+    fn push(&mut self, name: &str, role: ColumnRole, vector: VectorRef) -> Result<()> {
+        self.columns.push(PutColumn {
+            name: name.to_string(),
+            role,
+            vector,
+        });
+        Ok(())
+    }
+}
+
+impl PutOperation for PutData {
+    type Error = crate::error::Error;
+
+    fn add_key_column(&mut self, name: &str, vector: VectorRef) -> Result<()> {
+        self.push(name, ColumnRole::Key, vector)
+    }
+
+    fn add_version_column(&mut self, vector: VectorRef) -> Result<()> {
+        self.push(store_api::storage::consts::VERSION_COLUMN_NAME, ColumnRole::Version, vector)
+    }
+
+    fn add_value_column(&mut self, name: &str, vector: VectorRef) -> Result<()> {
+        self.push(name, ColumnRole::Value, vector)
+    }
+}
+
+/// A single mutation within a [`WriteBatch`]. Only `Put` exists today;
+/// `Delete` is reserved for when the write path grows explicit tombstones.
+#[derive(Clone)]
+pub enum Mutation {
+    Put(PutData),
+}
+
+/// The expected shape of a region's columns, as passed to
+/// `write_batch_util::new_write_batch` (name, logical type, nullability).
+pub type ColumnSpec = (&'static str, datatypes::type_id::LogicalTypeId, bool);
+
+/// An ordered sequence of [`Mutation`]s destined for one region. The batch
+/// carries no column schema; its mutations are not checked against one.
+#[derive(Clone, Default)]
+pub struct WriteBatch {
+    mutations: Vec<Mutation>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mutations(&self) -> &[Mutation] {
+        &self.mutations
+    }
+}
+
+impl WriteRequest for WriteBatch {
+    type Error = crate::error::Error;
+    type PutOp = PutData;
+
+    fn put(&mut self, data: PutData) -> Result<()> {
+        self.mutations.push(Mutation::Put(data));
+        Ok(())
+    }
+}
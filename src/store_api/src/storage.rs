@@ -0,0 +1,50 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage-engine-agnostic types shared between the `storage` crate and its
+//! consumers (table engines, benches, tests).
+
+use datatypes::vectors::VectorRef;
+
+/// Well-known column names reserved by the storage engine.
+pub mod consts {
+    /// Every row carries a version, used to order concurrent writes to the
+    /// same key and to classify superseding mutations (see the storage
+    /// crate's MVCC delta layer).
+    pub const VERSION_COLUMN_NAME: &str = "__version";
+}
+
+/// A region-local, monotonically increasing sequence number assigned to each
+/// mutation as it's appended to the WAL.
+pub type SequenceNumber = u64;
+
+/// Builder-style accumulator for a single put mutation's columns, keyed by
+/// role (primary key, version, or plain value column).
+pub trait PutOperation {
+    type Error;
+
+    fn add_key_column(&mut self, name: &str, vector: VectorRef) -> Result<(), Self::Error>;
+
+    fn add_version_column(&mut self, vector: VectorRef) -> Result<(), Self::Error>;
+
+    fn add_value_column(&mut self, name: &str, vector: VectorRef) -> Result<(), Self::Error>;
+}
+
+/// A batch of mutations destined for a single region's memtable/WAL.
+pub trait WriteRequest {
+    type Error;
+    type PutOp: PutOperation<Error = Self::Error>;
+
+    fn put(&mut self, data: Self::PutOp) -> Result<(), Self::Error>;
+}
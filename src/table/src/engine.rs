@@ -0,0 +1,70 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use async_trait::async_trait;
+
+use crate::quota::TableUsage;
+use crate::requests::{
+    AlterTableRequest, CreateTableRequest, DropTableRequest, OpenTableRequest, WriteTableRequest,
+};
+use crate::{Result, TableRef};
+
+/// Per-call context threaded through every [`TableEngine`] operation.
+#[derive(Debug, Clone, Default)]
+pub struct EngineContext {}
+
+/// Creates, opens, and manages tables for one storage engine implementation.
+#[async_trait]
+pub trait TableEngine: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn create_table(
+        &self,
+        ctx: &EngineContext,
+        request: CreateTableRequest,
+    ) -> Result<TableRef>;
+
+    async fn open_table(
+        &self,
+        ctx: &EngineContext,
+        request: OpenTableRequest,
+    ) -> Result<Option<TableRef>>;
+
+    async fn alter_table(&self, ctx: &EngineContext, request: AlterTableRequest) -> Result<TableRef>;
+
+    fn get_table(&self, ctx: &EngineContext, name: &str) -> Result<Option<TableRef>>;
+
+    fn table_exists(&self, ctx: &EngineContext, name: &str) -> bool;
+
+    async fn drop_table(&self, ctx: &EngineContext, request: DropTableRequest) -> Result<()>;
+
+    /// Applies a write to an existing table. For engines that track quota,
+    /// this is the enforcement point: `request.rows`/`request.bytes` are
+    /// reserved against the table's [`crate::quota::QuotaTracker`] before the
+    /// write is considered to have happened, and the write is rejected with
+    /// `Error::QuotaExceeded` rather than applied if it would not fit. The
+    /// default implementation is for engines that don't opt into quota
+    /// tracking and simply accept every write.
+    async fn write(&self, _ctx: &EngineContext, _request: WriteTableRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// Current row/byte usage against quota for `name`, for an admin API to
+    /// surface; `None` if the table doesn't exist or the engine doesn't
+    /// track quotas. The default implementation is for engines (or the
+    /// mocks exercising this trait) that don't opt into quota tracking.
+    fn table_usage(&self, _ctx: &EngineContext, _name: &str) -> Option<TableUsage> {
+        None
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod engine;
+pub mod error;
+pub mod metadata;
+pub mod quota;
+pub mod requests;
+
+use std::sync::Arc;
+
+use common_recordbatch::RecordBatch;
+
+pub use crate::error::Result;
+use crate::metadata::TableId;
+
+/// A queryable table, as returned by a [`crate::engine::TableEngine`].
+pub trait Table: Send + Sync {
+    fn table_id(&self) -> TableId;
+
+    fn record_batch(&self) -> &RecordBatch;
+}
+
+pub type TableRef = Arc<dyn Table>;
@@ -0,0 +1,67 @@
+// Copyright 2022 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Requests accepted by [`crate::engine::TableEngine`].
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct CreateTableRequest {
+    pub catalog_name: Option<String>,
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    /// Free-form engine options, e.g. `table_id`, or the quota knobs
+    /// consumed by `crate::quota` (`max_rows`, `max_bytes`).
+    pub table_options: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpenTableRequest {
+    pub catalog_name: Option<String>,
+    pub schema_name: Option<String>,
+    pub table_name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlterTableRequest {
+    pub catalog_name: Option<String>,
+    pub schema_name: Option<String>,
+    pub table_name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DropTableRequest {
+    pub catalog_name: Option<String>,
+    pub schema_name: Option<String>,
+    pub table_name: String,
+}
+
+/// One write (e.g. an `INSERT`) applied to a table through
+/// [`crate::engine::TableEngine::write`].
+///
+/// This crate doesn't depend on the `storage` crate's `WriteBatch`/`PutData`
+/// (engines own their storage representation; `table` only defines the
+/// engine-facing contract), so `rows`/`bytes` are the caller's own count of
+/// what it's about to apply — the same sizing an engine would otherwise do
+/// internally before touching its memtable. Engines that track quota (see
+/// `crate::quota::QuotaTracker`) reserve against these before the write is
+/// allowed to proceed.
+#[derive(Debug, Clone, Default)]
+pub struct WriteTableRequest {
+    pub catalog_name: Option<String>,
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub rows: u64,
+    pub bytes: u64,
+}